@@ -0,0 +1,135 @@
+//! Support code for the `hot-reload` feature: watching a file on disk
+//! and deserializing its contents whenever it changes.
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Mutex;
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// The serialization format to use when deserializing a watched file,
+/// inferred from its extension.
+enum FileFormat {
+    Json,
+    Ron,
+    Toml,
+}
+
+impl FileFormat {
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Some(FileFormat::Json),
+            Some("ron") => Some(FileFormat::Ron),
+            Some("toml") => Some(FileFormat::Toml),
+            _ => None,
+        }
+    }
+
+    fn deserialize<T: serde::de::DeserializeOwned>(&self, contents: &str) -> Option<T> {
+        match self {
+            FileFormat::Json => serde_json::from_str(contents).ok(),
+            FileFormat::Ron => ron::from_str(contents).ok(),
+            FileFormat::Toml => toml::from_str(contents).ok(),
+        }
+    }
+}
+
+/// A filesystem watch on a single tweak file, plus enough state to
+/// re-deserialize it when it changes.
+pub(crate) struct Watch {
+    path: PathBuf,
+    format: FileFormat,
+    // Kept alive only so the OS watch isn't torn down; never read directly.
+    _watcher: RecommendedWatcher,
+    events: Mutex<Receiver<notify::Result<Event>>>,
+}
+
+impl Watch {
+    /// Start watching `path`, if its extension names a format we understand.
+    /// Returns `None` (rather than erroring) if the watch can't be set up,
+    /// since hot-reloading is a convenience and shouldn't be fatal.
+    pub(crate) fn new(path: PathBuf) -> Option<Self> {
+        let format = FileFormat::from_path(&path)?;
+        let (tx, events) = channel();
+        let mut watcher = notify::recommended_watcher(tx).ok()?;
+        watcher.watch(&path, RecursiveMode::NonRecursive).ok()?;
+
+        Some(Watch {
+            path,
+            format,
+            _watcher: watcher,
+            events: Mutex::new(events),
+        })
+    }
+
+    /// Drain any pending filesystem events and, if the watched file was
+    /// modified, deserialize its new contents.
+    pub(crate) fn poll<T: serde::de::DeserializeOwned>(&self) -> Option<T> {
+        let events = self.events.lock().unwrap();
+        let mut changed = false;
+        while let Ok(event) = events.try_recv() {
+            if let Ok(event) = event {
+                if event.kind.is_modify() {
+                    changed = true;
+                }
+            }
+        }
+        drop(events);
+
+        if !changed {
+            return None;
+        }
+
+        let contents = std::fs::read_to_string(&self.path).ok()?;
+        self.format.deserialize(&contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(serde::Deserialize, PartialEq, Debug)]
+    struct Sample {
+        value: i32,
+    }
+
+    #[test]
+    fn from_path_dispatches_on_extension() {
+        assert!(matches!(
+            FileFormat::from_path(Path::new("tweaks.json")),
+            Some(FileFormat::Json)
+        ));
+        assert!(matches!(
+            FileFormat::from_path(Path::new("tweaks.ron")),
+            Some(FileFormat::Ron)
+        ));
+        assert!(matches!(
+            FileFormat::from_path(Path::new("tweaks.toml")),
+            Some(FileFormat::Toml)
+        ));
+        assert!(FileFormat::from_path(Path::new("tweaks.yaml")).is_none());
+        assert!(FileFormat::from_path(Path::new("tweaks")).is_none());
+    }
+
+    #[test]
+    fn deserialize_each_format() {
+        assert_eq!(
+            FileFormat::Json.deserialize::<Sample>(r#"{"value": 5}"#),
+            Some(Sample { value: 5 })
+        );
+        assert_eq!(
+            FileFormat::Ron.deserialize::<Sample>("(value: 5)"),
+            Some(Sample { value: 5 })
+        );
+        assert_eq!(
+            FileFormat::Toml.deserialize::<Sample>("value = 5"),
+            Some(Sample { value: 5 })
+        );
+    }
+
+    #[test]
+    fn deserialize_returns_none_on_malformed_contents() {
+        assert_eq!(FileFormat::Json.deserialize::<Sample>("not json"), None);
+    }
+}
@@ -6,37 +6,89 @@
 //! without having that data be mutable when the application is
 //! released.
 //!
-//! There are also two features available:
+//! There are also a few features available:
 //!
 //! 1. `force-dynamic` which allows replacing the value of a
 //!     `DirtyStatic` even in release mode.
 //! 2. `force-static` which disallows replacing the value of a
 //!     `DirtyStatic` even in debug mode.
+//! 3. `hot-reload` which adds a `DirtyStatic::from_path` constructor
+//!     that, in debug mode, re-reads and re-deserializes its value
+//!     from disk whenever the file changes. In release mode this
+//!     just bakes in the default value, pulling in no extra
+//!     dependencies.
+//! 4. `sync-dynamic` which, in debug mode, backs the `DirtyStatic`
+//!     with an `RwLock` instead of an `UnsafeCell`, exposing a safe
+//!     `read` and `replace`, so the value can be tweaked from one
+//!     thread while being read from another. In release mode (or
+//!     with `force-static`) this still compiles down to a plain
+//!     field access, with no lock and no atomics.
 
 #[cfg(all(feature = "force-static", feature = "force-dynamic"))]
 compile_error!("dirty_static: Cannot enable both the force-static and force-dynamic features.");
 
+#[cfg(all(feature = "sync-dynamic", feature = "hot-reload"))]
+compile_error!("dirty_static: sync-dynamic is not yet supported together with hot-reload.");
+
+#[cfg(all(
+    feature = "hot-reload",
+    any(
+        feature = "force-dynamic",
+        all(not(feature = "force-static"), debug_assertions)
+    )
+))]
+mod hot_reload;
+
 pub use internal::DirtyStatic;
 
-#[cfg(any(
-    feature = "force-dynamic",
-    all(not(feature = "force-static"), debug_assertions)
+#[cfg(all(
+    not(feature = "sync-dynamic"),
+    any(
+        feature = "force-dynamic",
+        all(not(feature = "force-static"), debug_assertions)
+    )
 ))]
 mod internal {
-    use std::cell::UnsafeCell;
+    use std::any::Any;
+    use std::cell::{RefCell, UnsafeCell};
+    use std::collections::HashMap;
     use std::ops::Deref;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    thread_local! {
+        // Keyed by the address of the owning DirtyStatic, so each instance
+        // can have its own per-thread override without needing a
+        // thread-local field per (generic) instance.
+        static OVERRIDES: RefCell<HashMap<usize, Box<dyn Any>>> = RefCell::new(HashMap::new());
+    }
 
     /// A container for a value which allows interior mutation
     /// only in debug mode. (Or when enabled via `force-dynamic`
     /// feature.)
-    pub struct DirtyStatic<T>(UnsafeCell<T>);
+    pub struct DirtyStatic<T> {
+        value: UnsafeCell<T>,
+        generation: AtomicU64,
+        #[cfg(feature = "hot-reload")]
+        watch: Option<crate::hot_reload::Watch>,
+    }
     unsafe impl<T> Sync for DirtyStatic<T> where T: Sync {}
 
-    impl<T> Deref for DirtyStatic<T> {
+    impl<T: 'static> Deref for DirtyStatic<T> {
         type Target = T;
 
         fn deref(&self) -> &Self::Target {
-            let ptr = self.0.get();
+            let key = self as *const Self as usize;
+            let overridden = OVERRIDES.with(|overrides| {
+                overrides
+                    .borrow()
+                    .get(&key)
+                    .map(|value| value.downcast_ref::<T>().unwrap() as *const T)
+            });
+            if let Some(ptr) = overridden {
+                return unsafe { &*ptr };
+            }
+
+            let ptr = self.value.get();
             unsafe { &*ptr }
         }
     }
@@ -53,7 +105,27 @@ mod internal {
         /// assert_eq!(*c, 100);
         /// ```
         pub const fn new(t: T) -> Self {
-            DirtyStatic(UnsafeCell::new(t))
+            DirtyStatic {
+                value: UnsafeCell::new(t),
+                generation: AtomicU64::new(0),
+                #[cfg(feature = "hot-reload")]
+                watch: None,
+            }
+        }
+
+        /// The number of times this DirtyStatic has been replaced.
+        /// Always starts at `0`. Compare against a previously observed
+        /// generation with [`DirtyStatic::is_dirty_since`] to cheaply
+        /// detect whether a hot-tweak has happened, e.g. to know when to
+        /// recompute a derived cache.
+        pub fn generation(&self) -> u64 {
+            self.generation.load(Ordering::Relaxed)
+        }
+
+        /// Returns `true` if this DirtyStatic has been replaced since the
+        /// given `last` generation was observed.
+        pub fn is_dirty_since(&self, last: u64) -> bool {
+            self.generation() != last
         }
 
         /// Replace the interior value of the DirtyStatic. Note that
@@ -99,8 +171,194 @@ mod internal {
         /// assert_eq!(*c, 100);
         /// ```
         pub unsafe fn replace(&self, t: T) {
-            let ptr = self.0.get();
-            *ptr = t
+            let ptr = self.value.get();
+            *ptr = t;
+            self.generation.fetch_add(1, Ordering::Relaxed);
+        }
+
+        /// Set the interior value, moving `val` in by value.
+        ///
+        /// # Safety
+        ///
+        /// The same caveats as [`DirtyStatic::replace`] apply: any
+        /// references to the interior value are invalidated. Accessing
+        /// this data is undefined behaviour.
+        pub unsafe fn set(&self, val: T) {
+            let ptr = self.value.get();
+            *ptr = val;
+            self.generation.fetch_add(1, Ordering::Relaxed);
+        }
+
+        /// Replace the interior value with `val`, returning the previous
+        /// value.
+        ///
+        /// # Safety
+        ///
+        /// The same caveats as [`DirtyStatic::replace`] apply: any
+        /// references to the interior value are invalidated. Accessing
+        /// this data is undefined behaviour.
+        pub unsafe fn swap(&self, val: T) -> T
+        where
+            T: Clone,
+        {
+            let ptr = self.value.get();
+            let old = std::mem::replace(&mut *ptr, val);
+            self.generation.fetch_add(1, Ordering::Relaxed);
+            old
+        }
+
+        /// Replace the interior value with its `Default`, returning the
+        /// previous value.
+        ///
+        /// # Safety
+        ///
+        /// The same caveats as [`DirtyStatic::replace`] apply: any
+        /// references to the interior value are invalidated. Accessing
+        /// this data is undefined behaviour.
+        pub unsafe fn take(&self) -> T
+        where
+            T: Default + Clone,
+        {
+            self.swap(T::default())
+        }
+    }
+
+    impl<T: Copy> DirtyStatic<T> {
+        /// Get a copy of the interior value. Safe: this only ever copies
+        /// the value out, and never hands out a reference into the
+        /// DirtyStatic.
+        pub fn get(&self) -> T {
+            let ptr = self.value.get();
+            unsafe { *ptr }
+        }
+    }
+
+    impl<T: 'static> DirtyStatic<T> {
+        /// Install `value` as an override of the interior value for the
+        /// duration of `f`, visible (via `Deref` and [`DirtyStatic::read`])
+        /// only on the current thread, then restore whatever was there
+        /// before (even if `f` panics). [`DirtyStatic::get`] does not see
+        /// the override: it always copies out the underlying value.
+        ///
+        /// Unlike [`DirtyStatic::replace`], this is safe: the override is
+        /// thread-local, so parallel tests that each call `with_override`
+        /// on the same DirtyStatic don't race with each other.
+        ///
+        /// # Examples
+        ///
+        /// ```rust,no_run
+        /// // In debug mode
+        /// use dirty_static::DirtyStatic;
+        ///
+        /// let c = DirtyStatic::new(100);
+        ///
+        /// c.with_override(200, || {
+        ///     assert_eq!(*c, 200);
+        /// });
+        ///
+        /// assert_eq!(*c, 100);
+        /// ```
+        ///
+        /// ```rust,no_run
+        /// // In release mode
+        /// use dirty_static::DirtyStatic;
+        ///
+        /// let c = DirtyStatic::new(100);
+        ///
+        /// c.with_override(200, || {
+        ///     // Ignored: a DirtyStatic can never be overridden in
+        ///     // release mode (or with the `force-static` feature).
+        ///     assert_eq!(*c, 100);
+        /// });
+        ///
+        /// assert_eq!(*c, 100);
+        /// ```
+        pub fn with_override<R>(&self, value: T, f: impl FnOnce() -> R) -> R {
+            let key = self as *const Self as usize;
+            let prior = OVERRIDES.with(|overrides| {
+                overrides
+                    .borrow_mut()
+                    .insert(key, Box::new(value) as Box<dyn Any>)
+            });
+            let _guard = OverrideGuard { key, prior };
+
+            f()
+        }
+
+        /// Borrow the interior value. Equivalent to `*dirty_static`, but
+        /// matches the `read` method exposed by the `sync-dynamic`
+        /// feature, so code that borrows via `read()` also compiles here.
+        /// (`replace` stays `unsafe` in this build configuration.)
+        pub fn read(&self) -> &T {
+            self.deref()
+        }
+    }
+
+    /// Restores the previous thread-local override (or removes it, if
+    /// there wasn't one) when dropped, so a panic inside `with_override`'s
+    /// closure can't leave a stale override behind.
+    struct OverrideGuard {
+        key: usize,
+        prior: Option<Box<dyn Any>>,
+    }
+
+    impl Drop for OverrideGuard {
+        fn drop(&mut self) {
+            OVERRIDES.with(|overrides| {
+                let mut overrides = overrides.borrow_mut();
+                match self.prior.take() {
+                    Some(prior) => {
+                        overrides.insert(self.key, prior);
+                    }
+                    None => {
+                        overrides.remove(&self.key);
+                    }
+                }
+            });
+        }
+    }
+
+    #[cfg(feature = "hot-reload")]
+    impl<T> DirtyStatic<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        /// Create a new DirtyStatic backed by a file on disk. The file is
+        /// watched for changes, and whenever it changes, it's re-read and
+        /// re-deserialized (as JSON, RON or TOML, chosen by the file's
+        /// extension) to replace the interior value. The file is not read
+        /// here: `default` is used until [`DirtyStatic::poll`] picks up a
+        /// change to the file, and for good if the watch cannot be
+        /// established (e.g. the extension is not one of the three above).
+        ///
+        /// Reloading is driven by polling: call [`DirtyStatic::poll`] once
+        /// per frame (or however often is appropriate) to pick up changes.
+        /// The value is only ever replaced inside `poll`, on the calling
+        /// thread; this crate spawns no threads of its own, though the
+        /// `notify` watcher delivers its events from its own thread.
+        pub fn from_path<P: AsRef<std::path::Path>>(default: T, path: P) -> Self {
+            DirtyStatic {
+                value: UnsafeCell::new(default),
+                generation: AtomicU64::new(0),
+                watch: crate::hot_reload::Watch::new(path.as_ref().to_path_buf()),
+            }
+        }
+
+        /// Check the watched file (if this DirtyStatic was created via
+        /// [`DirtyStatic::from_path`]) for changes, and replace the
+        /// interior value if it has changed. Does nothing otherwise.
+        ///
+        /// # Safety
+        ///
+        /// The same caveats as [`DirtyStatic::replace`] apply: any
+        /// references to the interior value are invalidated by a
+        /// successful poll.
+        pub unsafe fn poll(&self) {
+            if let Some(watch) = &self.watch {
+                if let Some(value) = watch.poll() {
+                    self.replace(value);
+                }
+            }
         }
     }
 
@@ -124,6 +382,98 @@ mod internal {
             unsafe { c.replace("Replacement value".to_string()) };
             assert_eq!(&*c, "Replacement value");
         }
+
+        #[test]
+        fn generation_increments_on_replace() {
+            let c = DirtyStatic::new(100);
+            let gen0 = c.generation();
+
+            unsafe { c.replace(200) };
+
+            assert!(c.is_dirty_since(gen0));
+            assert!(!c.is_dirty_since(c.generation()));
+        }
+
+        #[test]
+        fn with_override_restores_value_afterwards() {
+            let c = DirtyStatic::new(100);
+
+            let result = c.with_override(200, || {
+                assert_eq!(*c, 200);
+                *c * 2
+            });
+
+            assert_eq!(result, 400);
+            assert_eq!(*c, 100);
+        }
+
+        #[test]
+        fn with_override_restores_value_on_panic() {
+            let c = DirtyStatic::new(100);
+
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                c.with_override(200, || panic!("boom"));
+            }));
+
+            assert!(result.is_err());
+            assert_eq!(*c, 100);
+        }
+
+        #[test]
+        fn get_set_swap_take() {
+            let c = DirtyStatic::new(100);
+            assert_eq!(c.get(), 100);
+
+            unsafe { c.set(200) };
+            assert_eq!(c.get(), 200);
+
+            let old = unsafe { c.swap(300) };
+            assert_eq!(old, 200);
+            assert_eq!(c.get(), 300);
+
+            let taken = unsafe { c.take() };
+            assert_eq!(taken, 300);
+            assert_eq!(c.get(), 0);
+        }
+
+        #[test]
+        #[cfg(feature = "hot-reload")]
+        fn from_path_picks_up_file_changes() {
+            #[derive(serde::Deserialize)]
+            struct Sample {
+                value: i32,
+            }
+
+            let mut path = std::env::temp_dir();
+            path.push(format!(
+                "dirty_static_from_path_picks_up_file_changes_{}.json",
+                std::process::id()
+            ));
+            std::fs::write(&path, r#"{"value": 1}"#).unwrap();
+
+            // `default` is used until a change to the file is observed,
+            // so the initial value is 0, not the 1 already on disk.
+            let c = DirtyStatic::from_path(Sample { value: 0 }, &path);
+            assert_eq!((*c).value, 0);
+
+            std::fs::write(&path, r#"{"value": 2}"#).unwrap();
+
+            // notify delivers filesystem events asynchronously, so poll
+            // for a while rather than assuming the event has arrived by
+            // the time we get here.
+            let mut picked_up = false;
+            for _ in 0..50 {
+                unsafe { c.poll() };
+                if (*c).value == 2 {
+                    picked_up = true;
+                    break;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(20));
+            }
+
+            std::fs::remove_file(&path).ok();
+            assert!(picked_up, "poll() never picked up the file change");
+        }
     }
 }
 
@@ -149,9 +499,186 @@ mod internal {
             DirtyStatic(t)
         }
 
+        /// Replace the interior value. Note that this will do nothing
+        /// unless running in debug mode, or enabling the `force-dynamic`
+        /// feature.
+        ///
+        /// # Safety
+        ///
+        /// The same caveats as the dynamic module's `replace` apply: any
+        /// references to the interior value are invalidated. Accessing
+        /// this data is undefined behaviour. (In this build configuration
+        /// replace is a no-op, but the signature must still match the
+        /// dynamic module's so application code compiles unmodified
+        /// regardless of build mode.)
+        #[cfg(not(feature = "sync-dynamic"))]
         pub unsafe fn replace(&self, _t: T) {
             eprintln!("WARNING: Can't replace in release mode!");
         }
+
+        /// Replace the interior value. Note that this will do nothing
+        /// unless running in debug mode, or enabling the `force-dynamic`
+        /// feature.
+        ///
+        /// Unlike the plain no-`sync-dynamic` build, this is safe: with
+        /// `sync-dynamic` enabled, the dynamic module's `replace` is also
+        /// safe (it takes a write lock instead of mutating through an
+        /// `UnsafeCell`), so this stays safe for source compatibility.
+        #[cfg(feature = "sync-dynamic")]
+        pub fn replace(&self, _t: T) {
+            eprintln!("WARNING: Can't replace in release mode!");
+        }
+
+        /// Always `0`, since a DirtyStatic can never be replaced in
+        /// release mode (or with the `force-static` feature).
+        pub fn generation(&self) -> u64 {
+            0
+        }
+
+        /// Always `false`, since a DirtyStatic can never be replaced in
+        /// release mode (or with the `force-static` feature).
+        pub fn is_dirty_since(&self, _last: u64) -> bool {
+            false
+        }
+
+        /// Runs `f` against the unmodified interior value. `value` is
+        /// ignored, since a DirtyStatic can never be overridden in release
+        /// mode (or with the `force-static` feature).
+        pub fn with_override<R>(&self, _value: T, f: impl FnOnce() -> R) -> R {
+            f()
+        }
+
+        /// Borrow the baked-in value. Matches the `read`/`replace` API
+        /// exposed by the `sync-dynamic` feature, compiling down to a
+        /// plain field access with no lock.
+        pub fn read(&self) -> &T {
+            &self.0
+        }
+
+        /// A no-op: `val` is dropped, and the baked-in value is left
+        /// untouched.
+        ///
+        /// # Safety
+        ///
+        /// The same caveats as [`DirtyStatic::replace`] apply: any
+        /// references to the interior value are invalidated. Accessing
+        /// this data is undefined behaviour. (In this build configuration
+        /// set is a no-op, but the signature must still match the
+        /// dynamic module's so application code compiles unmodified
+        /// regardless of build mode.)
+        #[cfg(not(feature = "sync-dynamic"))]
+        pub unsafe fn set(&self, _val: T) {}
+
+        /// A no-op: `val` is dropped, and the baked-in value is left
+        /// untouched.
+        ///
+        /// Unlike the plain no-`sync-dynamic` build, this is safe: with
+        /// `sync-dynamic` enabled, the dynamic module's `set` is also
+        /// safe (it takes a write lock instead of mutating through an
+        /// `UnsafeCell`), so this stays safe for source compatibility.
+        #[cfg(feature = "sync-dynamic")]
+        pub fn set(&self, _val: T) {}
+
+        /// Returns a clone of the baked-in value. `val` is dropped: there
+        /// is nothing to swap with in release mode (or with the
+        /// `force-static` feature).
+        ///
+        /// # Safety
+        ///
+        /// The same caveats as [`DirtyStatic::replace`] apply: any
+        /// references to the interior value are invalidated. Accessing
+        /// this data is undefined behaviour. (In this build configuration
+        /// swap never actually replaces anything, but the signature must
+        /// still match the dynamic module's so application code compiles
+        /// unmodified regardless of build mode.)
+        #[cfg(not(feature = "sync-dynamic"))]
+        pub unsafe fn swap(&self, _val: T) -> T
+        where
+            T: Clone,
+        {
+            self.0.clone()
+        }
+
+        /// Returns a clone of the baked-in value. `val` is dropped: there
+        /// is nothing to swap with in release mode (or with the
+        /// `force-static` feature).
+        ///
+        /// Unlike the plain no-`sync-dynamic` build, this is safe: with
+        /// `sync-dynamic` enabled, the dynamic module's `swap` is also
+        /// safe, so this stays safe for source compatibility.
+        #[cfg(feature = "sync-dynamic")]
+        pub fn swap(&self, _val: T) -> T
+        where
+            T: Clone,
+        {
+            self.0.clone()
+        }
+
+        /// Returns a clone of the baked-in value, which is left
+        /// untouched: there is nothing to take in release mode (or with
+        /// the `force-static` feature). Bounded by `Default` as well as
+        /// `Clone` (even though this implementation doesn't need
+        /// `Default`) to match the dynamic module's `take`, so the same
+        /// calling code compiles against either.
+        ///
+        /// # Safety
+        ///
+        /// The same caveats as [`DirtyStatic::replace`] apply: any
+        /// references to the interior value are invalidated. Accessing
+        /// this data is undefined behaviour. (In this build configuration
+        /// take never actually replaces anything, but the signature must
+        /// still match the dynamic module's so application code compiles
+        /// unmodified regardless of build mode.)
+        #[cfg(not(feature = "sync-dynamic"))]
+        pub unsafe fn take(&self) -> T
+        where
+            T: Default + Clone,
+        {
+            self.0.clone()
+        }
+
+        /// Returns a clone of the baked-in value, which is left
+        /// untouched: there is nothing to take in release mode (or with
+        /// the `force-static` feature). Bounded by `Default` as well as
+        /// `Clone` (even though this implementation doesn't need
+        /// `Default`) to match the dynamic module's `take`, so the same
+        /// calling code compiles against either.
+        ///
+        /// Unlike the plain no-`sync-dynamic` build, this is safe: with
+        /// `sync-dynamic` enabled, the dynamic module's `take` is also
+        /// safe, so this stays safe for source compatibility.
+        #[cfg(feature = "sync-dynamic")]
+        pub fn take(&self) -> T
+        where
+            T: Default + Clone,
+        {
+            self.0.clone()
+        }
+    }
+
+    impl<T: Copy> DirtyStatic<T> {
+        /// Get a copy of the baked-in value.
+        pub fn get(&self) -> T {
+            self.0
+        }
+    }
+
+    #[cfg(feature = "hot-reload")]
+    impl<T> DirtyStatic<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        /// Create a new DirtyStatic with the given default value. Note
+        /// that in release mode (or with the `force-static` feature)
+        /// `path` is ignored entirely: the file is never read, and no
+        /// watch is established, so no I/O happens.
+        pub fn from_path<P: AsRef<std::path::Path>>(default: T, _path: P) -> Self {
+            DirtyStatic::new(default)
+        }
+
+        /// Does nothing in release mode (or with the `force-static`
+        /// feature), since there is no watched file to poll.
+        pub unsafe fn poll(&self) {}
     }
 
     #[cfg(test)]
@@ -166,7 +693,12 @@ mod internal {
             assert_eq!(&*c, "Hello");
         }
 
+        // `replace` is `unsafe` in the plain build, but safe once
+        // `sync-dynamic` is enabled (to match the dynamic module's
+        // `replace`) - wrap in `unsafe` and silence the resulting
+        // "unnecessary unsafe" lint so these tests compile either way.
         #[test]
+        #[allow(unused_unsafe)]
         fn refresh_value_does_nothing() {
             let text = "Hello".to_string();
             let c = DirtyStatic::new(text);
@@ -174,10 +706,396 @@ mod internal {
             unsafe { c.replace("Replacement value".to_string()) };
             assert_eq!(&*c, "Hello");
         }
+
+        #[test]
+        #[allow(unused_unsafe)]
+        fn generation_never_changes() {
+            let c = DirtyStatic::new(100);
+
+            unsafe { c.replace(200) };
+
+            assert_eq!(c.generation(), 0);
+            assert!(!c.is_dirty_since(0));
+        }
+
+        #[test]
+        fn with_override_does_nothing() {
+            let c = DirtyStatic::new(100);
+
+            let result = c.with_override(200, || *c);
+
+            assert_eq!(result, 100);
+            assert_eq!(*c, 100);
+        }
+
+        // `set`/`swap`/`take` are `unsafe` in the plain build, but safe
+        // once `sync-dynamic` is enabled (to match the dynamic module's
+        // `set`/`swap`/`take`) - wrap in `unsafe` and silence the
+        // resulting "unnecessary unsafe" lint so this test compiles
+        // either way.
+        #[test]
+        #[allow(unused_unsafe)]
+        fn get_set_swap_take_preserve_value() {
+            let c = DirtyStatic::new(100);
+            assert_eq!(c.get(), 100);
+
+            unsafe { c.set(200) };
+            assert_eq!(c.get(), 100);
+
+            let old = unsafe { c.swap(300) };
+            assert_eq!(old, 100);
+            assert_eq!(c.get(), 100);
+
+            let taken = unsafe { c.take() };
+            assert_eq!(taken, 100);
+            assert_eq!(c.get(), 100);
+        }
+
+        #[test]
+        #[cfg(feature = "hot-reload")]
+        fn from_path_ignores_the_file() {
+            #[derive(serde::Deserialize)]
+            struct Sample {
+                value: i32,
+            }
+
+            let mut path = std::env::temp_dir();
+            path.push(format!(
+                "dirty_static_from_path_ignores_the_file_{}.json",
+                std::process::id()
+            ));
+            std::fs::write(&path, r#"{"value": 1}"#).unwrap();
+
+            let c = DirtyStatic::from_path(Sample { value: 0 }, &path);
+            unsafe { c.poll() };
+
+            std::fs::remove_file(&path).ok();
+            assert_eq!(c.value, 0);
+        }
     }
 }
 
-#[cfg(test)]
+#[cfg(all(
+    feature = "sync-dynamic",
+    any(
+        feature = "force-dynamic",
+        all(not(feature = "force-static"), debug_assertions)
+    )
+))]
+mod internal {
+    use std::any::Any;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::marker::PhantomData;
+    use std::ops::Deref;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{RwLock, RwLockReadGuard};
+
+    thread_local! {
+        // Keyed by the address of the owning DirtyStatic, so each instance
+        // can have its own per-thread override without needing a
+        // thread-local field per (generic) instance.
+        static OVERRIDES: RefCell<HashMap<usize, Box<dyn Any>>> = RefCell::new(HashMap::new());
+    }
+
+    /// A container for a value which allows synchronized interior
+    /// mutation only in debug mode. (Or when enabled via
+    /// `force-dynamic` feature.) Backed by an `RwLock` rather than an
+    /// `UnsafeCell`, so it's sound to tweak the value from one thread
+    /// while another reads it, at the cost of locking on every access.
+    pub struct DirtyStatic<T> {
+        value: RwLock<T>,
+        generation: AtomicU64,
+    }
+
+    /// The guard returned by [`DirtyStatic::read`]: either a genuine
+    /// `RwLock` read guard, or a thread-local override installed by
+    /// [`DirtyStatic::with_override`]. There's deliberately no `Deref`
+    /// impl on `DirtyStatic` itself (unlike the other build
+    /// configurations): going through the lock is unavoidable here, so
+    /// callers borrow via `read()` instead of `*dirty_static`.
+    pub enum Read<'a, T> {
+        Guard(RwLockReadGuard<'a, T>),
+        Override(*const T, PhantomData<&'a T>),
+    }
+
+    impl<'a, T> Deref for Read<'a, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            match self {
+                Read::Guard(guard) => guard,
+                Read::Override(ptr, _) => unsafe { &**ptr },
+            }
+        }
+    }
+
+    impl<T> DirtyStatic<T> {
+        /// Create a new DirtyStatic with the given interior value.
+        pub const fn new(t: T) -> Self {
+            DirtyStatic {
+                value: RwLock::new(t),
+                generation: AtomicU64::new(0),
+            }
+        }
+
+        /// The number of times this DirtyStatic has been replaced.
+        /// Always starts at `0`. Compare against a previously observed
+        /// generation with [`DirtyStatic::is_dirty_since`] to cheaply
+        /// detect whether a hot-tweak has happened.
+        pub fn generation(&self) -> u64 {
+            self.generation.load(Ordering::Relaxed)
+        }
+
+        /// Returns `true` if this DirtyStatic has been replaced since the
+        /// given `last` generation was observed.
+        pub fn is_dirty_since(&self, last: u64) -> bool {
+            self.generation() != last
+        }
+
+        /// Replace the interior value. Unlike
+        /// [`DirtyStatic::replace`](struct.DirtyStatic.html) in builds
+        /// without `sync-dynamic`, this is safe: it takes a write lock for
+        /// the duration of the swap, so it's sound to call from one
+        /// thread while another holds a [`DirtyStatic::read`] guard.
+        ///
+        /// # Panics
+        ///
+        /// Panics if the lock is poisoned, i.e. some thread panicked
+        /// while holding it.
+        pub fn replace(&self, t: T) {
+            *self.value.write().unwrap() = t;
+            self.generation.fetch_add(1, Ordering::Relaxed);
+        }
+
+        /// Set the interior value, moving `val` in by value. Safe for
+        /// the same reason as [`DirtyStatic::replace`].
+        ///
+        /// # Panics
+        ///
+        /// Panics if the lock is poisoned, i.e. some thread panicked
+        /// while holding it.
+        pub fn set(&self, val: T) {
+            *self.value.write().unwrap() = val;
+            self.generation.fetch_add(1, Ordering::Relaxed);
+        }
+
+        /// Replace the interior value with `val`, returning the previous
+        /// value. Safe for the same reason as [`DirtyStatic::replace`].
+        ///
+        /// # Panics
+        ///
+        /// Panics if the lock is poisoned, i.e. some thread panicked
+        /// while holding it.
+        pub fn swap(&self, val: T) -> T
+        where
+            T: Clone,
+        {
+            let mut guard = self.value.write().unwrap();
+            let old = std::mem::replace(&mut *guard, val);
+            drop(guard);
+            self.generation.fetch_add(1, Ordering::Relaxed);
+            old
+        }
+
+        /// Replace the interior value with its `Default`, returning the
+        /// previous value. Safe for the same reason as
+        /// [`DirtyStatic::replace`].
+        ///
+        /// # Panics
+        ///
+        /// Panics if the lock is poisoned, i.e. some thread panicked
+        /// while holding it.
+        pub fn take(&self) -> T
+        where
+            T: Default + Clone,
+        {
+            self.swap(T::default())
+        }
+    }
+
+    impl<T: Copy> DirtyStatic<T> {
+        /// Get a copy of the interior value.
+        ///
+        /// # Panics
+        ///
+        /// Panics if the lock is poisoned, i.e. some thread panicked
+        /// while holding it.
+        pub fn get(&self) -> T {
+            *self.value.read().unwrap()
+        }
+    }
+
+    impl<T: 'static> DirtyStatic<T> {
+        /// Install `value` as an override of the interior value for the
+        /// duration of `f`, visible (via [`DirtyStatic::read`]) only on
+        /// the current thread, then restore whatever was there before
+        /// (even if `f` panics).
+        ///
+        /// Unlike [`DirtyStatic::replace`], this never touches the
+        /// `RwLock`: the override is thread-local, so parallel tests that
+        /// each call `with_override` on the same DirtyStatic don't race
+        /// with each other, or block on another thread's read.
+        pub fn with_override<R>(&self, value: T, f: impl FnOnce() -> R) -> R {
+            let key = self as *const Self as usize;
+            let prior = OVERRIDES.with(|overrides| {
+                overrides
+                    .borrow_mut()
+                    .insert(key, Box::new(value) as Box<dyn Any>)
+            });
+            let _guard = OverrideGuard { key, prior };
+
+            f()
+        }
+
+        /// Take a read lock on the interior value, unless it's been
+        /// overridden on the current thread by
+        /// [`DirtyStatic::with_override`].
+        ///
+        /// # Panics
+        ///
+        /// Panics if the lock is poisoned, i.e. some thread panicked
+        /// while holding it.
+        pub fn read(&self) -> Read<'_, T> {
+            let key = self as *const Self as usize;
+            let overridden = OVERRIDES.with(|overrides| {
+                overrides
+                    .borrow()
+                    .get(&key)
+                    .map(|value| value.downcast_ref::<T>().unwrap() as *const T)
+            });
+            if let Some(ptr) = overridden {
+                return Read::Override(ptr, PhantomData);
+            }
+
+            Read::Guard(self.value.read().unwrap())
+        }
+    }
+
+    /// Restores the previous thread-local override (or removes it, if
+    /// there wasn't one) when dropped, so a panic inside `with_override`'s
+    /// closure can't leave a stale override behind.
+    struct OverrideGuard {
+        key: usize,
+        prior: Option<Box<dyn Any>>,
+    }
+
+    impl Drop for OverrideGuard {
+        fn drop(&mut self) {
+            OVERRIDES.with(|overrides| {
+                let mut overrides = overrides.borrow_mut();
+                match self.prior.take() {
+                    Some(prior) => {
+                        overrides.insert(self.key, prior);
+                    }
+                    None => {
+                        overrides.remove(&self.key);
+                    }
+                }
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn create_value() {
+            let text = "Hello".to_string();
+            let c = DirtyStatic::new(text);
+
+            assert_eq!(&*c.read(), "Hello");
+        }
+
+        #[test]
+        fn refresh_value() {
+            let text = "Hello".to_string();
+            let c = DirtyStatic::new(text);
+
+            c.replace("Replacement value".to_string());
+            assert_eq!(&*c.read(), "Replacement value");
+        }
+
+        #[test]
+        fn generation_increments_on_replace() {
+            let c = DirtyStatic::new(100);
+            let gen0 = c.generation();
+
+            c.replace(200);
+
+            assert!(c.is_dirty_since(gen0));
+            assert!(!c.is_dirty_since(c.generation()));
+        }
+
+        #[test]
+        fn get_set_swap_take() {
+            let c = DirtyStatic::new(100);
+            assert_eq!(c.get(), 100);
+
+            c.set(200);
+            assert_eq!(c.get(), 200);
+
+            let old = c.swap(300);
+            assert_eq!(old, 200);
+            assert_eq!(c.get(), 300);
+
+            let taken = c.take();
+            assert_eq!(taken, 300);
+            assert_eq!(c.get(), 0);
+        }
+
+        #[test]
+        fn with_override_restores_value_afterwards() {
+            let c = DirtyStatic::new(100);
+
+            let result = c.with_override(200, || {
+                assert_eq!(*c.read(), 200);
+                *c.read() * 2
+            });
+
+            assert_eq!(result, 400);
+            assert_eq!(*c.read(), 100);
+        }
+
+        #[test]
+        fn with_override_restores_value_on_panic() {
+            let c = DirtyStatic::new(100);
+
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                c.with_override(200, || panic!("boom"));
+            }));
+
+            assert!(result.is_err());
+            assert_eq!(*c.read(), 100);
+        }
+
+        #[test]
+        fn concurrent_read_and_replace() {
+            use std::sync::Arc;
+            use std::thread;
+
+            let c = Arc::new(DirtyStatic::new(0));
+            let writer = {
+                let c = Arc::clone(&c);
+                thread::spawn(move || {
+                    for i in 1..=100 {
+                        c.replace(i);
+                    }
+                })
+            };
+
+            for _ in 0..100 {
+                let _ = *c.read();
+            }
+
+            writer.join().unwrap();
+            assert_eq!(*c.read(), 100);
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "sync-dynamic")))]
 mod feature_tests {
     use super::DirtyStatic;
 
@@ -235,3 +1153,65 @@ mod feature_tests {
         _assert_dynamic();
     }
 }
+
+#[cfg(all(test, feature = "sync-dynamic"))]
+mod sync_feature_tests {
+    use super::DirtyStatic;
+
+    // With `sync-dynamic` enabled, `replace` is a safe fn both on the
+    // RwLock-backed dynamic module and on the static module (which this
+    // same code compiles against when `force-static` is also enabled).
+    fn _assert_static() {
+        let c = DirtyStatic::new(10);
+        c.replace(20);
+        assert_eq!(*c.read(), 10);
+    }
+
+    fn _assert_dynamic() {
+        let c = DirtyStatic::new(10);
+        c.replace(20);
+        assert_eq!(*c.read(), 20);
+    }
+
+    #[test]
+    #[cfg(all(
+        debug_assertions,
+        not(any(feature = "force-static", feature = "force-dynamic"))
+    ))]
+    fn feature_test() {
+        _assert_dynamic();
+    }
+
+    #[test]
+    #[cfg(all(
+        not(debug_assertions),
+        not(any(feature = "force-static", feature = "force-dynamic"))
+    ))]
+    fn feature_test() {
+        _assert_static();
+    }
+
+    #[test]
+    #[cfg(all(debug_assertions, feature = "force-static"))]
+    fn feature_test() {
+        _assert_static();
+    }
+
+    #[test]
+    #[cfg(all(debug_assertions, feature = "force-dynamic"))]
+    fn feature_test() {
+        _assert_dynamic();
+    }
+
+    #[test]
+    #[cfg(all(not(debug_assertions), feature = "force-static"))]
+    fn feature_test() {
+        _assert_static();
+    }
+
+    #[test]
+    #[cfg(all(not(debug_assertions), feature = "force-dynamic"))]
+    fn feature_test() {
+        _assert_dynamic();
+    }
+}